@@ -0,0 +1,126 @@
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Condvar, Mutex};
+
+use request::{ReadWrite, Request};
+use util::RefinedTcpStream;
+
+type LiveConnections = Arc<(Mutex<usize>, Condvar)>;
+
+/// Which framing a [`ClientConnection`] negotiated for the socket handed to
+/// it by `Server::accept`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Http1,
+    Http2Cleartext,
+}
+
+/// A connection accepted by a [`crate::Server`], yielding the request sent
+/// over it. At most one request is yielded: the first [`Request`] takes over
+/// the connection's streams, so later requests on the same connection are
+/// not read.
+pub struct ClientConnection {
+    writer: Option<RefinedTcpStream>,
+    reader: Option<RefinedTcpStream>,
+    protocol: Protocol,
+    live_connections: LiveConnections,
+}
+
+impl ClientConnection {
+    pub fn new(writer: RefinedTcpStream, reader: RefinedTcpStream) -> ClientConnection {
+        ClientConnection::with_protocol(
+            writer,
+            reader,
+            Protocol::Http1,
+            Arc::new((Mutex::new(0), Condvar::new())),
+        )
+    }
+
+    pub fn with_protocol(
+        writer: RefinedTcpStream,
+        reader: RefinedTcpStream,
+        protocol: Protocol,
+        live_connections: LiveConnections,
+    ) -> ClientConnection {
+        *live_connections.0.lock().unwrap() += 1;
+
+        ClientConnection {
+            writer: Some(writer),
+            reader: Some(reader),
+            protocol,
+            live_connections,
+        }
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// Claims the raw, unparsed stream for this connection instead of
+    /// iterating it for HTTP/1 requests. This is how an h2c connection
+    /// (`protocol() == Protocol::Http2Cleartext`) is actually handed to the
+    /// caller: tiny-http only sniffs the preface, it doesn't speak HTTP/2
+    /// itself, so the caller is expected to drive their own framing on the
+    /// returned stream. Returns `None` if the streams were already taken
+    /// (e.g. by iterating this connection as HTTP/1).
+    pub fn into_raw_stream(mut self) -> Option<Box<dyn ReadWrite + Send>> {
+        match (self.reader.take(), self.writer.take()) {
+            (Some(reader), Some(writer)) => Some(Box::new(RawStream { reader, writer })),
+            _ => None,
+        }
+    }
+}
+
+struct RawStream {
+    reader: RefinedTcpStream,
+    writer: RefinedTcpStream,
+}
+
+impl Read for RawStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl Write for RawStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for ClientConnection {
+    fn drop(&mut self) {
+        let (count, drained) = &*self.live_connections;
+        let mut count = count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            drained.notify_all();
+        }
+    }
+}
+
+impl Iterator for ClientConnection {
+    type Item = io::Result<Request>;
+
+    fn next(&mut self) -> Option<io::Result<Request>> {
+        if self.protocol != Protocol::Http1 {
+            // No HTTP/1 requests to parse here; the caller must call
+            // `into_raw_stream()` instead to get at the h2c connection.
+            return None;
+        }
+
+        let (reader, writer) = match (self.reader.take(), self.writer.take()) {
+            (Some(reader), Some(writer)) => (reader, writer),
+            _ => return None,
+        };
+
+        match Request::from_stream(reader, writer) {
+            Ok(Some(request)) => Some(Ok(request)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
@@ -1,19 +1,19 @@
-use std::ascii::{AsciiCast, StrAsciiExt};
-use std::fmt::{Formatter, FormatError, Show};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use ascii::{AsciiStr, AsciiString, IntoAsciiString};
 
 /// Status code of a request or response.
-#[deriving(Eq, PartialEq, Clone, Show, Ord, PartialOrd)]
-#[stable]
-pub struct StatusCode(pub uint);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StatusCode(pub u16);
 
 impl StatusCode {
-    #[stable]
     /// Returns the status code as a number.
-    pub fn as_uint(&self) -> uint {
-        match *self { StatusCode(n) => n }
+    pub fn as_uint(&self) -> u16 {
+        self.0
     }
 
-    #[stable]
     /// Returns the default reason phrase for this status code.
     /// For example the status code 404 corresponds to "Not Found".
     pub fn get_default_reason_phrase(&self) -> &'static str {
@@ -62,177 +62,151 @@ impl StatusCode {
             503 => "Service Unavailable",
             504 => "Gateway Time-out",
             505 => "HTTP Version not supported",
-            _ => "Unknown"
+            _ => "Unknown",
         }
     }
 }
 
-impl Equiv<uint> for StatusCode {
-    fn equiv(&self, other: &uint) -> bool {
-        self.as_uint() == *other
+impl fmt::Display for StatusCode {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.0)
+    }
+}
+
+impl PartialEq<u16> for StatusCode {
+    fn eq(&self, other: &u16) -> bool {
+        self.0 == *other
     }
 }
 
 /// Represents a HTTP header.
-/// 
-/// The easiest way to create a `Header` object is to call `from_str`.
-/// 
+///
+/// The easiest way to create a `Header` object is to call `.parse()` on a string.
+///
 /// ```
-/// let header: Header = from_str("Content-Type: text/plain").unwrap();
+/// # use tiny_http::Header;
+/// let header: Header = "Content-Type: text/plain".parse().unwrap();
 /// ```
-#[deriving(Clone)]
-#[unstable]
+#[derive(Debug, Clone)]
 pub struct Header {
     pub field: HeaderField,
     pub value: String,
 }
 
-impl ::std::from_str::FromStr for Header {
-    fn from_str(input: &str) -> Option<Header> {
-        let elems = input.splitn(':', 2).map(|e| e.to_string()).collect::<Vec<String>>();
+impl FromStr for Header {
+    type Err = ();
 
-        if elems.len() <= 1 {
-            return None;
-        }
+    fn from_str(input: &str) -> Result<Header, ()> {
+        let mut elems = input.splitn(2, ':');
 
-        let field = match from_str(elems.get(0).as_slice().trim()) {
-            None => return None,
-            Some(f) => f
-        };
+        let field = elems.next().ok_or(())?.trim().parse()?;
+        let value = elems.next().ok_or(())?.trim().to_string();
 
-        Some(Header {
-            field: field,
-            value: elems.get(1).as_slice().trim().to_string()
-        })
+        Ok(Header { field, value })
     }
 }
 
-impl Show for Header {
-    fn fmt(&self, formatter: &mut Formatter) -> Result<(), FormatError> {
-        (format!("{}: {}", self.field, self.value)).fmt(formatter)
+impl fmt::Display for Header {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}: {}", self.field, self.value)
     }
 }
 
 /// Field of a header (eg. `Content-Type`, `Content-Length`, etc.)
-/// 
-/// Comparaison between two `HeaderField`s ignores case.
-#[unstable]
-#[deriving(Clone)]
-pub struct HeaderField(Vec<Ascii>);
+///
+/// Comparison between two `HeaderField`s ignores case.
+#[derive(Debug, Clone)]
+pub struct HeaderField(AsciiString);
 
 impl HeaderField {
-    fn as_str<'a>(&'a self) -> &'a [Ascii] {
-        match self { &HeaderField(ref s) => s.as_slice() }
+    fn as_ascii_str(&self) -> &AsciiStr {
+        &self.0
     }
-}
 
-impl ::std::from_str::FromStr for HeaderField {
-    fn from_str(s: &str) -> Option<HeaderField> {
-        s.trim().to_ascii_opt().map(|s| HeaderField(Vec::from_slice(s)))
+    /// Case-insensitive comparison against any string-like value, e.g.
+    /// `header.field.equiv(&"content-type")`.
+    pub fn equiv<S: AsRef<str>>(&self, other: &S) -> bool {
+        self.as_ascii_str().as_str().eq_ignore_ascii_case(other.as_ref())
     }
 }
 
-impl IntoStr for HeaderField {
-    fn into_string(self) -> String {
-        match self { HeaderField(s) => s.into_string() }
+impl FromStr for HeaderField {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<HeaderField, ()> {
+        s.trim().into_ascii_string().map(HeaderField).map_err(|_| ())
     }
 }
 
-impl Show for HeaderField {
-    fn fmt(&self, formatter: &mut Formatter) -> Result<(), FormatError> {
-        let method = self.as_str();
-        method.as_str_ascii().fmt(formatter)
+impl fmt::Display for HeaderField {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.as_ascii_str().as_str())
     }
 }
 
 impl PartialEq for HeaderField {
     fn eq(&self, other: &HeaderField) -> bool {
-        self.as_str().eq_ignore_case(other.as_str())
+        self.as_ascii_str().eq_ignore_ascii_case(other.as_ascii_str())
     }
 }
 
 impl Eq for HeaderField {}
 
-impl<S: Str> Equiv<S> for HeaderField {
-    fn equiv(&self, other: &S) -> bool {
-        other.as_slice().eq_ignore_ascii_case(self.as_str().as_str_ascii())
-    }
-}
-
-
 /// HTTP method (eg. `GET`, `POST`, etc.)
-/// 
-/// The user chooses the method he wants.
-/// 
-/// Comparaison between two `Method`s ignores case.
-#[unstable]
-#[deriving(Clone)]
-pub struct Method(Vec<Ascii>);
+///
+/// Comparison between two `Method`s ignores case.
+#[derive(Debug, Clone)]
+pub struct Method(AsciiString);
 
 impl Method {
-    fn as_str<'a>(&'a self) -> &'a [Ascii] {
-        match self { &Method(ref s) => s.as_slice() }
+    fn as_ascii_str(&self) -> &AsciiStr {
+        &self.0
     }
 }
 
-impl ::std::from_str::FromStr for Method {
-    fn from_str(s: &str) -> Option<Method> {
-        s.to_ascii_opt().map(|s| Method(Vec::from_slice(s)))
-    }
-}
+impl FromStr for Method {
+    type Err = ();
 
-impl IntoStr for Method {
-    fn into_string(self) -> String {
-        match self { Method(s) => s.into_string() }
+    fn from_str(s: &str) -> Result<Method, ()> {
+        s.into_ascii_string().map(Method).map_err(|_| ())
     }
 }
 
-impl Show for Method {
-    fn fmt(&self, formatter: &mut Formatter) -> Result<(), FormatError> {
-        let method = self.as_str();
-        method.as_str_ascii().fmt(formatter)
+impl fmt::Display for Method {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.as_ascii_str().as_str())
     }
 }
 
 impl PartialEq for Method {
     fn eq(&self, other: &Method) -> bool {
-        self.as_str().eq_ignore_case(other.as_str())
+        self.as_ascii_str().eq_ignore_ascii_case(other.as_ascii_str())
     }
 }
 
 impl Eq for Method {}
 
-impl<S: Str> Equiv<S> for Method {
-    fn equiv(&self, other: &S) -> bool {
-        other.as_slice().eq_ignore_ascii_case(self.as_str().as_str_ascii())
-    }
-}
-
 /// HTTP version (usually 1.0 or 1.1).
-#[unstable]
-#[deriving(Clone, PartialEq, Eq, Ord)]
-pub struct HTTPVersion(pub uint, pub uint);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HTTPVersion(pub u32, pub u32);
 
-impl Show for HTTPVersion {
-    fn fmt(&self, formatter: &mut Formatter) -> Result<(), FormatError> {
-        let (major, minor) = match self { &HTTPVersion(m, n) => (m, n) };
-        (format!("{}.{}", major, minor)).fmt(formatter)
+impl fmt::Display for HTTPVersion {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}.{}", self.0, self.1)
     }
 }
 
 impl PartialOrd for HTTPVersion {
     fn partial_cmp(&self, other: &HTTPVersion) -> Option<Ordering> {
-        let (my_major, my_minor) = match self { &HTTPVersion(m, n) => (m, n) };
-        let (other_major, other_minor) = match other { &HTTPVersion(m, n) => (m, n) };
-
-        if my_major != other_major {
-            return my_major.partial_cmp(&other_major)
-        }
-
-        my_minor.partial_cmp(&other_minor)
+        Some(self.cmp(other))
     }
 }
 
+impl Ord for HTTPVersion {
+    fn cmp(&self, other: &HTTPVersion) -> Ordering {
+        (self.0, self.1).cmp(&(other.0, other.1))
+    }
+}
 
 #[cfg(test)]
 mod test {
@@ -240,11 +214,11 @@ mod test {
 
     #[test]
     fn test_parse_header() {
-        let header: Header = from_str("Content-Type: text/html").unwrap();
+        let header: Header = "Content-Type: text/html".parse().unwrap();
 
         assert!(header.field.equiv(&"content-type"));
-        assert!(header.value.as_slice() == "text/html");
+        assert_eq!(header.value, "text/html");
 
-        assert!(from_str::<Header>("hello world").is_none());
+        assert!("hello world".parse::<Header>().is_err());
     }
 }
@@ -4,8 +4,13 @@
 
 extern crate log;
 extern crate ascii;
+extern crate base64;
+extern crate brotli;
 extern crate chrono;
 extern crate chunked_transfer;
+extern crate flate2;
+extern crate rustls;
+extern crate sha1;
 extern crate url;
 
 use std::io;
@@ -13,14 +18,17 @@ use std::net;
 use std::net::{Shutdown, TcpStream, TcpListener};
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
-use std::sync::Arc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
-use client::ClientConnection;
+use rustls::{Certificate, PrivateKey, ServerConfig, ServerConnection, StreamOwned};
+
+use client::{ClientConnection, Protocol};
 use util::RefinedTcpStream;
 
 pub use common::{HTTPVersion, Header, HeaderField, Method, StatusCode};
-pub use request::{ReadWrite, Request};
-pub use response::{Response, ResponseBox};
+pub use request::{ReadWrite, Request, WebSocketUpgradeError};
+pub use response::{BodySize, ContentEncoding, MessageBody, Response, ResponseBox};
 
 mod client;
 mod common;
@@ -32,15 +40,113 @@ mod util;
 pub struct Server {
     listener: TcpListener,
     is_shutting_down: Arc<AtomicBool>,
+    tls_config: Option<Arc<ServerConfig>>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    h2c: bool,
+    live_connections: Arc<(Mutex<usize>, Condvar)>,
+}
+
+/// Certificate chain and private key used to terminate TLS on a [`Server`].
+pub struct SslConfig {
+    pub cert_chain: Vec<Certificate>,
+    pub private_key: PrivateKey,
+}
+
+/// Configuration accepted by [`Server::new`].
+///
+/// Built with [`HttpServerOptions::new`] and customized through its builder
+/// methods. Marked `#[non_exhaustive]` so new knobs can be added without
+/// breaking callers.
+#[non_exhaustive]
+pub struct HttpServerOptions {
+    pub addr: String,
+    pub ssl: Option<SslConfig>,
+    pub read_timeout: Option<Duration>,
+    pub write_timeout: Option<Duration>,
+    /// Detect the HTTP/2 cleartext (h2c) connection preface on each accepted
+    /// socket and mark matching connections as `Protocol::Http2Cleartext`
+    /// instead of handing them to the HTTP/1 parser. tiny-http does no
+    /// HTTP/2 framing itself: such a connection yields no requests, and its
+    /// unread bytes (preface included) are taken through
+    /// `ClientConnection::into_raw_stream`.
+    pub h2c: bool,
+}
+
+impl HttpServerOptions {
+    pub fn new(addr: String) -> HttpServerOptions {
+        HttpServerOptions {
+            addr,
+            ssl: None,
+            read_timeout: None,
+            write_timeout: None,
+            h2c: false,
+        }
+    }
+
+    pub fn ssl(mut self, ssl: SslConfig) -> HttpServerOptions {
+        self.ssl = Some(ssl);
+        self
+    }
+
+    pub fn read_timeout(mut self, timeout: Duration) -> HttpServerOptions {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    pub fn write_timeout(mut self, timeout: Duration) -> HttpServerOptions {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    pub fn h2c(mut self, h2c: bool) -> HttpServerOptions {
+        self.h2c = h2c;
+        self
+    }
 }
 
+/// The first bytes of the HTTP/2 connection preface (RFC 7540 section 3.5),
+/// sent by an h2c client instead of an HTTP/1 request line.
+const H2C_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// How long `accept()` will wait for a client to send enough bytes to
+/// confirm or rule out the h2c preface before giving up and treating the
+/// connection as HTTP/1.
+const H2C_PEEK_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How long `Drop for Server` waits for live connections to drain before
+/// closing the listener regardless. Bounded, unlike `wait_for_shutdown(None)`:
+/// dropping a `Server` is the common case in tests, `main()` returning, and
+/// signal-handler cleanup, and none of those should be able to hang forever
+/// on an idle keep-alive socket or a wedged handler thread. Callers who want
+/// an unbounded (or differently bounded) drain should call
+/// `wait_for_shutdown` explicitly before dropping.
+const DROP_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
 impl Server {
-    pub fn new(addr: String) -> Result<Server, io::Error>{
-        let listener = net::TcpListener::bind(addr)?;
+    pub fn new(options: HttpServerOptions) -> Result<Server, io::Error> {
+        let listener = net::TcpListener::bind(&options.addr)?;
+
+        let tls_config = match options.ssl {
+            Some(ssl) => {
+                let config = ServerConfig::builder()
+                    .with_safe_defaults()
+                    .with_no_client_auth()
+                    .with_single_cert(ssl.cert_chain, ssl.private_key)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+                Some(Arc::new(config))
+            }
+            None => None,
+        };
 
         Ok(Server{
             listener,
             is_shutting_down: Arc::new(AtomicBool::new(false)),
+            tls_config,
+            read_timeout: options.read_timeout,
+            write_timeout: options.write_timeout,
+            h2c: options.h2c,
+            live_connections: Arc::new((Mutex::new(0), Condvar::new())),
         })
     }
 
@@ -50,6 +156,11 @@ impl Server {
         Ok(Server{
             listener,
             is_shutting_down: self.is_shutting_down.clone(),
+            tls_config: self.tls_config.clone(),
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+            h2c: self.h2c,
+            live_connections: self.live_connections.clone(),
         })
     }
 
@@ -58,18 +169,122 @@ impl Server {
 
         let (socket, _) = self.listener.accept()
             .map_err(AcceptError::Accept)?;
+        err_if_false(!self.is_shutting_down.load(Ordering::Relaxed), AcceptError::ShuttingDown())?;
+
+        if let Some(timeout) = self.read_timeout {
+            socket.set_read_timeout(Some(timeout)).map_err(AcceptError::Accept)?;
+        }
+        if let Some(timeout) = self.write_timeout {
+            socket.set_write_timeout(Some(timeout)).map_err(AcceptError::Accept)?;
+        }
+
+        // h2c is cleartext-only: a TLS session already has its own framing
+        // negotiated through ALPN, so there's no preface to sniff.
+        let protocol = if self.h2c && self.tls_config.is_none() {
+            if Self::peeks_as_h2c_preface(&socket, self.read_timeout).map_err(AcceptError::Accept)? {
+                Protocol::Http2Cleartext
+            } else {
+                Protocol::Http1
+            }
+        } else {
+            Protocol::Http1
+        };
 
-        let (read_closable, write_closable) = RefinedTcpStream::new(socket);
-        Ok(ClientConnection::new(write_closable, read_closable))
+        let (read_closable, write_closable) = match self.tls_config {
+            Some(ref tls_config) => {
+                let connection = ServerConnection::new(tls_config.clone())
+                    .map_err(AcceptError::Tls)?;
+                RefinedTcpStream::new(StreamOwned::new(connection, socket))
+            }
+            None => RefinedTcpStream::new(socket),
+        };
+
+        Ok(ClientConnection::with_protocol(
+            write_closable,
+            read_closable,
+            protocol,
+            self.live_connections.clone(),
+        ))
     }
 
-    fn shutdown(&mut self) -> Result<(), ShutdownError> {
+    /// Peeks at the start of `socket` without consuming it, so the parser
+    /// the connection gets routed to still sees the full preface.
+    ///
+    /// Bounded by its own short timeout regardless of the user-configured
+    /// `read_timeout` (which defaults to none): this call runs inline in the
+    /// single-threaded accept loop, so a client that connects and then sends
+    /// nothing must not be able to wedge `accept()` for every other client.
+    /// The socket's previous read timeout is restored before returning.
+    fn peeks_as_h2c_preface(
+        socket: &TcpStream,
+        previous_read_timeout: Option<Duration>,
+    ) -> Result<bool, io::Error> {
+        socket.set_read_timeout(Some(H2C_PEEK_TIMEOUT))?;
+        let deadline = Instant::now() + H2C_PEEK_TIMEOUT;
+        let mut buf = [0u8; H2C_PREFACE.len()];
+        let result = loop {
+            match socket.peek(&mut buf) {
+                // only a prefix of the preface has arrived so far: wait for
+                // the rest of it until the deadline
+                Ok(read)
+                    if read > 0
+                        && read < buf.len()
+                        && buf[..read] == H2C_PREFACE[..read]
+                        && Instant::now() < deadline =>
+                {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+                other => break other,
+            }
+        };
+        socket.set_read_timeout(previous_read_timeout)?;
+
+        match result {
+            Ok(read) => Ok(read == buf.len() && buf == *H2C_PREFACE),
+            Err(ref err)
+                if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut =>
+            {
+                Ok(false)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Stops accepting new connections and blocks until every
+    /// [`client::ClientConnection`] that's already live has finished (or
+    /// `timeout` elapses, if given), then wakes a thread blocked in
+    /// `accept()` by connecting to the listener (see `close_listener`); the
+    /// listening socket itself stays open until the `Server` is dropped. This lets
+    /// in-flight responses finish instead of being cut off mid-write, the
+    /// way the old immediate shutdown could.
+    pub fn wait_for_shutdown(&self, timeout: Option<Duration>) -> Result<(), ShutdownError> {
         self.is_shutting_down.store(true, Ordering::Relaxed);
 
+        let (count, drained) = &*self.live_connections;
+        let guard = count.lock().unwrap();
+
+        match timeout {
+            Some(timeout) => {
+                let _guard = drained
+                    .wait_timeout_while(guard, timeout, |count| *count > 0)
+                    .unwrap();
+            }
+            None => {
+                let _guard = drained.wait_while(guard, |count| *count > 0).unwrap();
+            }
+        }
+
+        self.close_listener()
+    }
+
+    /// Unblocks a thread parked in `accept()` by briefly connecting to the
+    /// listener ourselves, then shuts that connection down. `is_shutting_down`
+    /// makes the woken `accept()` return `AcceptError::ShuttingDown` instead
+    /// of serving it.
+    fn close_listener(&self) -> Result<(), ShutdownError> {
         let addr = self.listener.local_addr()
             .map_err(ShutdownError::LocalAddr)?;
 
-        // Connect briefly to ourselves to unblock the accept thread
         let stream = TcpStream::connect(addr)
             .map_err(ShutdownError::Connect)?;
 
@@ -83,6 +298,7 @@ impl Server {
 pub enum AcceptError {
     Accept(io::Error),
     ShuttingDown(),
+    Tls(rustls::Error),
 }
 
 #[derive(Debug)]
@@ -95,7 +311,12 @@ pub enum ShutdownError {
 
 impl Drop for Server {
     fn drop(&mut self) {
-        let _ = self.shutdown();
+        // Give live connections a bounded window to finish in-flight
+        // responses, then close the listener regardless -- see
+        // `DROP_SHUTDOWN_TIMEOUT`. An unbounded wait here would turn scope
+        // exit into a silent hang for any caller who didn't opt into
+        // `wait_for_shutdown` themselves.
+        let _ = self.wait_for_shutdown(Some(DROP_SHUTDOWN_TIMEOUT));
     }
 }
 
@@ -107,3 +328,63 @@ fn err_if_false<E>(value: bool, err: E) -> Result<(), E> {
         Err(err)
     }
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn detects_h2c_preface_without_consuming_it() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(H2C_PREFACE).unwrap();
+
+        let (socket, _) = listener.accept().unwrap();
+        assert!(Server::peeks_as_h2c_preface(&socket, None).unwrap());
+
+        // the bytes must still be there for whichever parser gets picked
+        let mut buf = [0u8; H2C_PREFACE.len()];
+        assert_eq!(socket.peek(&mut buf).unwrap(), buf.len());
+        assert_eq!(&buf, H2C_PREFACE);
+    }
+
+    #[test]
+    fn a_client_that_sends_nothing_is_not_mistaken_for_h2c() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let _client = TcpStream::connect(addr).unwrap();
+        let (socket, _) = listener.accept().unwrap();
+
+        assert!(!Server::peeks_as_h2c_preface(&socket, None).unwrap());
+    }
+
+    #[test]
+    fn wait_for_shutdown_blocks_until_live_connections_drop() {
+        use std::thread;
+
+        let server = Server::new(HttpServerOptions::new("127.0.0.1:0".to_owned())).unwrap();
+        let live_connections = server.live_connections.clone();
+
+        let stream = TcpStream::connect(server.listener.local_addr().unwrap()).unwrap();
+        let (read_half, write_half) = RefinedTcpStream::new(stream);
+        let connection =
+            ClientConnection::with_protocol(write_half, read_half, Protocol::Http1, live_connections);
+
+        let handle = thread::spawn(move || server.wait_for_shutdown(None));
+
+        // give wait_for_shutdown a moment to start blocking on the live
+        // connection before we drop it
+        thread::sleep(Duration::from_millis(50));
+        assert!(!handle.is_finished());
+
+        drop(connection);
+
+        handle.join().unwrap().unwrap();
+    }
+}
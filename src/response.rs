@@ -0,0 +1,290 @@
+use std::io::{self, Read, Write};
+use std::mem;
+
+use chunked_transfer::Encoder as ChunkedEncoder;
+
+use common::{Header, StatusCode};
+
+mod compression;
+
+pub use self::compression::Encoding as ContentEncoding;
+
+/// How much data a [`MessageBody`] has left to give, if known ahead of time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodySize {
+    /// No hint is available; the body is read to exhaustion.
+    None,
+    /// The body has no data at all.
+    Zero,
+    /// The body has exactly this many bytes left.
+    Sized(usize),
+    /// The body has data, but its total length isn't known upfront.
+    Unsized,
+}
+
+/// A response body that's pulled one chunk at a time instead of read all at
+/// once, so a [`Response`] can stream generated or proxied content without
+/// buffering it or knowing its length in advance.
+pub trait MessageBody: Send {
+    /// A hint used to pick `Content-Length` vs chunked transfer encoding;
+    /// see [`BodySize`].
+    fn size_hint(&self) -> BodySize;
+
+    /// Returns the next chunk of the body, or `None` once it's exhausted.
+    fn poll_chunk(&mut self) -> io::Result<Option<Vec<u8>>>;
+}
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Adapts any [`Read`] plus an optional known length into a [`MessageBody`],
+/// which is how [`Response::new`] keeps accepting a plain reader.
+struct ReaderBody<R> {
+    reader: R,
+    remaining: Option<usize>,
+}
+
+impl<R: Read + Send> MessageBody for ReaderBody<R> {
+    fn size_hint(&self) -> BodySize {
+        match self.remaining {
+            Some(0) => BodySize::Zero,
+            Some(n) => BodySize::Sized(n),
+            None => BodySize::Unsized,
+        }
+    }
+
+    fn poll_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let want = match self.remaining {
+            Some(0) => return Ok(None),
+            Some(n) => n.min(CHUNK_SIZE),
+            None => CHUNK_SIZE,
+        };
+
+        let mut buf = vec![0u8; want];
+        let read = self.reader.read(&mut buf)?;
+        if read == 0 {
+            Ok(None)
+        } else {
+            buf.truncate(read);
+            if let Some(remaining) = self.remaining.as_mut() {
+                *remaining -= read;
+            }
+            Ok(Some(buf))
+        }
+    }
+}
+
+impl MessageBody for Vec<u8> {
+    fn size_hint(&self) -> BodySize {
+        if self.is_empty() {
+            BodySize::Zero
+        } else {
+            BodySize::Sized(self.len())
+        }
+    }
+
+    fn poll_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(mem::take(self)))
+        }
+    }
+}
+
+impl MessageBody for String {
+    fn size_hint(&self) -> BodySize {
+        if self.is_empty() {
+            BodySize::Zero
+        } else {
+            BodySize::Sized(self.len())
+        }
+    }
+
+    fn poll_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(mem::take(self).into_bytes()))
+        }
+    }
+}
+
+impl MessageBody for &'static str {
+    fn size_hint(&self) -> BodySize {
+        if self.is_empty() {
+            BodySize::Zero
+        } else {
+            BodySize::Sized(self.len())
+        }
+    }
+
+    fn poll_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.is_empty() {
+            Ok(None)
+        } else {
+            let chunk = self.as_bytes().to_vec();
+            *self = "";
+            Ok(Some(chunk))
+        }
+    }
+}
+
+/// An HTTP response.
+pub struct Response {
+    status_code: StatusCode,
+    headers: Vec<Header>,
+    body: Box<dyn MessageBody>,
+    compression: Option<Vec<ContentEncoding>>,
+}
+
+/// Kept as an alias for source compatibility with code that named the boxed
+/// form of `Response` explicitly; `Response` is boxed internally now.
+pub type ResponseBox = Response;
+
+impl Response {
+    pub fn new<R>(
+        status_code: StatusCode,
+        headers: Vec<Header>,
+        data: R,
+        data_length: Option<usize>,
+    ) -> Response
+    where
+        R: Read + Send + 'static,
+    {
+        Response::from_body(
+            status_code,
+            headers,
+            ReaderBody {
+                reader: data,
+                remaining: data_length,
+            },
+        )
+    }
+
+    /// Like [`Response::new`], but takes a [`MessageBody`] directly instead
+    /// of a `Read` plus a known length.
+    pub fn from_body<B>(status_code: StatusCode, headers: Vec<Header>, body: B) -> Response
+    where
+        B: MessageBody + 'static,
+    {
+        Response {
+            status_code,
+            headers,
+            body: Box::new(body),
+            compression: None,
+        }
+    }
+
+    pub fn with_status_code(mut self, code: StatusCode) -> Response {
+        self.status_code = code;
+        self
+    }
+
+    pub fn with_header(mut self, header: Header) -> Response {
+        self.headers.push(header);
+        self
+    }
+
+    /// Opts this response into negotiating one of `encodings` against the
+    /// request's `Accept-Encoding` header. Still subject to the
+    /// content-type and minimum-size gates in [`compression`] &mdash; small
+    /// or already-compressed bodies are sent verbatim regardless.
+    pub fn with_compression(mut self, encodings: Vec<ContentEncoding>) -> Response {
+        self.compression = Some(encodings);
+        self
+    }
+
+    fn content_type(&self) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|header| header.field.equiv(&"Content-Type"))
+            .map(|header| header.value.as_str())
+    }
+
+    /// Writes the status line, headers and body to `writer`. `Sized` bodies
+    /// get a `Content-Length`, `Unsized` bodies are sent chunked, and `Zero`
+    /// bodies are skipped entirely. If this response opted into compression
+    /// and the negotiated encoding and content type allow it, a `Sized` body
+    /// is compressed and sent chunked instead.
+    pub fn raw_print<W: Write>(
+        mut self,
+        mut writer: W,
+        accept_encoding: Option<&str>,
+    ) -> io::Result<()> {
+        let data_length = match self.body.size_hint() {
+            BodySize::Zero => Some(0),
+            BodySize::Sized(len) => Some(len),
+            BodySize::None | BodySize::Unsized => None,
+        };
+
+        let negotiated = self.compression.as_ref().and_then(|encodings| {
+            if compression::should_compress(self.content_type(), data_length) {
+                compression::negotiate(accept_encoding, encodings)
+            } else {
+                None
+            }
+        });
+
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\n",
+            self.status_code.as_uint(),
+            self.status_code.get_default_reason_phrase(),
+        )?;
+
+        // Content-Length and Transfer-Encoding are always ours to recompute
+        // (one or the other, depending on the body), but Content-Encoding is
+        // left alone unless we're about to emit a different one ourselves --
+        // a caller serving an already-encoded body and setting this header
+        // themselves must not have it silently stripped.
+        self.headers.retain(|header| {
+            !header.field.equiv(&"Content-Length") && !header.field.equiv(&"Transfer-Encoding")
+        });
+
+        if let Some(encoding) = negotiated {
+            self.headers.retain(|header| !header.field.equiv(&"Content-Encoding"));
+
+            let mut body = Vec::new();
+            while let Some(chunk) = self.body.poll_chunk()? {
+                body.extend_from_slice(&chunk);
+            }
+            let compressed = compression::compress(encoding, &body)?;
+
+            self.headers.push(compression::content_encoding_header(encoding));
+            self.headers.push("Transfer-Encoding: chunked".parse().unwrap());
+            write_headers(&mut writer, &self.headers)?;
+
+            let mut encoder = ChunkedEncoder::new(writer);
+            return encoder.write_all(&compressed);
+        }
+
+        match data_length {
+            Some(len) => {
+                self.headers.push(format!("Content-Length: {}", len).parse().unwrap());
+                write_headers(&mut writer, &self.headers)?;
+
+                while let Some(chunk) = self.body.poll_chunk()? {
+                    writer.write_all(&chunk)?;
+                }
+                Ok(())
+            }
+            None => {
+                self.headers.push("Transfer-Encoding: chunked".parse().unwrap());
+                write_headers(&mut writer, &self.headers)?;
+
+                let mut encoder = ChunkedEncoder::new(writer);
+                while let Some(chunk) = self.body.poll_chunk()? {
+                    encoder.write_all(&chunk)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn write_headers<W: Write>(writer: &mut W, headers: &[Header]) -> io::Result<()> {
+    for header in headers {
+        write!(writer, "{}\r\n", header)?;
+    }
+    write!(writer, "\r\n")
+}
@@ -0,0 +1,133 @@
+use std::io::{self, Write};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use common::Header;
+
+/// Content encodings [`super::Response::with_compression`] can negotiate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+    Brotli,
+}
+
+impl Encoding {
+    fn token(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Brotli => "br",
+        }
+    }
+}
+
+/// Bodies below this many bytes aren't worth the compression overhead.
+pub const MIN_COMPRESSIBLE_LEN: usize = 860;
+
+/// Picks the first of `supported` (in the response's preference order) that
+/// also appears in the client's `Accept-Encoding` header.
+pub fn negotiate(accept_encoding: Option<&str>, supported: &[Encoding]) -> Option<Encoding> {
+    let accept_encoding = accept_encoding?;
+    let requested: Vec<&str> = accept_encoding.split(',').map(|tok| tok.trim()).collect();
+
+    supported
+        .iter()
+        .copied()
+        .find(|encoding| requested.iter().any(|tok| *tok == encoding.token()))
+}
+
+/// Whether a response with this `Content-Type` and body length is worth
+/// compressing. Already-compressed media and tiny bodies are excluded, and
+/// so is any body whose length isn't known upfront: compressing it would
+/// mean buffering an unbounded/streamed body in memory, which is exactly
+/// what `MessageBody` exists to avoid.
+pub fn should_compress(content_type: Option<&str>, data_length: Option<usize>) -> bool {
+    let data_length = match data_length {
+        Some(len) => len,
+        None => return false,
+    };
+
+    if data_length < MIN_COMPRESSIBLE_LEN {
+        return false;
+    }
+
+    content_type.is_some_and(is_compressible_mime)
+}
+
+fn is_compressible_mime(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+
+    match mime.as_str() {
+        "image/svg+xml" => true,
+        _ if mime.starts_with("image/") || mime.starts_with("video/") || mime.starts_with("audio/") => false,
+        "application/zip" | "application/gzip" | "application/x-gzip" | "font/woff2" => false,
+        _ if mime.starts_with("text/") => true,
+        "application/json" | "application/javascript" | "application/xml" | "application/xhtml+xml" => true,
+        _ => false,
+    }
+}
+
+pub fn compress(encoding: Encoding, data: &[u8]) -> io::Result<Vec<u8>> {
+    match encoding {
+        Encoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        Encoding::Brotli => {
+            let mut output = Vec::new();
+            let params = brotli::enc::BrotliEncoderParams::default();
+            brotli::BrotliCompress(&mut &data[..], &mut output, &params)?;
+            Ok(output)
+        }
+    }
+}
+
+pub fn content_encoding_header(encoding: Encoding) -> Header {
+    format!("Content-Encoding: {}", encoding.token())
+        .parse()
+        .expect("well-formed header")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn negotiate_prefers_earlier_supported_encoding() {
+        let supported = [Encoding::Brotli, Encoding::Gzip];
+        assert_eq!(
+            negotiate(Some("gzip, br"), &supported),
+            Some(Encoding::Brotli),
+        );
+        assert_eq!(negotiate(Some("gzip"), &supported), Some(Encoding::Gzip));
+        assert_eq!(negotiate(Some("deflate"), &supported), None);
+        assert_eq!(negotiate(None, &supported), None);
+    }
+
+    #[test]
+    fn compressible_mime_classification() {
+        assert!(is_compressible_mime("text/html; charset=utf-8"));
+        assert!(is_compressible_mime("application/json"));
+        assert!(is_compressible_mime("image/svg+xml"));
+
+        assert!(!is_compressible_mime("image/png"));
+        assert!(!is_compressible_mime("video/mp4"));
+        assert!(!is_compressible_mime("audio/ogg"));
+        assert!(!is_compressible_mime("application/zip"));
+        assert!(!is_compressible_mime("font/woff2"));
+    }
+
+    #[test]
+    fn should_compress_rejects_tiny_and_unsized_bodies() {
+        assert!(should_compress(Some("text/plain"), Some(MIN_COMPRESSIBLE_LEN)));
+        assert!(!should_compress(Some("text/plain"), Some(MIN_COMPRESSIBLE_LEN - 1)));
+        assert!(!should_compress(Some("text/plain"), None));
+        assert!(!should_compress(None, Some(MIN_COMPRESSIBLE_LEN)));
+    }
+}
@@ -0,0 +1,3 @@
+mod refined_tcp_stream;
+
+pub use self::refined_tcp_stream::RefinedTcpStream;
@@ -1,6 +1,9 @@
 use std::io::Result as IoResult;
 use std::io::{Read, Write};
 use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+
+use rustls::{ServerConnection, StreamOwned};
 
 pub struct RefinedTcpStream {
     stream: Stream,
@@ -10,6 +13,15 @@ pub struct RefinedTcpStream {
 
 pub enum Stream {
     Http(TcpStream),
+    /// A `rustls::StreamOwned` session can't be cloned into independent
+    /// read/write halves the way a `TcpStream` can, so both halves share one
+    /// session behind a lock instead -- a read and a write on the same
+    /// connection serialize through it. Half-closing is also coarser than
+    /// the plain-TCP case: `Drop` below shuts down the underlying socket
+    /// directly rather than sending a TLS `close_notify` alert, so a peer
+    /// strictly checking for a clean TLS shutdown may see this as a
+    /// truncated session instead of a graceful close.
+    Https(Arc<Mutex<StreamOwned<ServerConnection, TcpStream>>>),
 }
 
 impl From<TcpStream> for Stream {
@@ -19,6 +31,13 @@ impl From<TcpStream> for Stream {
     }
 }
 
+impl From<StreamOwned<ServerConnection, TcpStream>> for Stream {
+    #[inline]
+    fn from(stream: StreamOwned<ServerConnection, TcpStream>) -> Stream {
+        Stream::Https(Arc::new(Mutex::new(stream)))
+    }
+}
+
 impl RefinedTcpStream {
     pub fn new<S>(stream: S) -> (RefinedTcpStream, RefinedTcpStream)
     where
@@ -26,8 +45,12 @@ impl RefinedTcpStream {
     {
         let stream = stream.into();
 
+        // A plain `TcpStream` can be cloned into independent read/write halves,
+        // but a `rustls::StreamOwned` session can't be — both halves share the
+        // same session behind a lock instead.
         let read = match stream {
             Stream::Http(ref stream) => Stream::Http(stream.try_clone().unwrap()),
+            Stream::Https(ref session) => Stream::Https(session.clone()),
         };
 
         let read = RefinedTcpStream {
@@ -48,6 +71,7 @@ impl RefinedTcpStream {
     pub fn peer_addr(&mut self) -> IoResult<SocketAddr> {
         match self.stream {
             Stream::Http(ref mut stream) => stream.peer_addr(),
+            Stream::Https(ref session) => session.lock().unwrap().sock.peer_addr(),
         }
     }
 }
@@ -58,6 +82,7 @@ impl Drop for RefinedTcpStream {
             match self.stream {
                 // ignoring outcome
                 Stream::Http(ref mut stream) => stream.shutdown(Shutdown::Read).ok(),
+                Stream::Https(ref session) => session.lock().unwrap().sock.shutdown(Shutdown::Read).ok(),
             };
         }
 
@@ -65,6 +90,7 @@ impl Drop for RefinedTcpStream {
             match self.stream {
                 // ignoring outcome
                 Stream::Http(ref mut stream) => stream.shutdown(Shutdown::Write).ok(),
+                Stream::Https(ref session) => session.lock().unwrap().sock.shutdown(Shutdown::Write).ok(),
             };
         }
     }
@@ -74,6 +100,7 @@ impl Read for RefinedTcpStream {
     fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
         match self.stream {
             Stream::Http(ref mut stream) => stream.read(buf),
+            Stream::Https(ref session) => session.lock().unwrap().read(buf),
         }
     }
 }
@@ -82,12 +109,14 @@ impl Write for RefinedTcpStream {
     fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
         match self.stream {
             Stream::Http(ref mut stream) => stream.write(buf),
+            Stream::Https(ref session) => session.lock().unwrap().write(buf),
         }
     }
 
     fn flush(&mut self) -> IoResult<()> {
         match self.stream {
             Stream::Http(ref mut stream) => stream.flush(),
+            Stream::Https(ref session) => session.lock().unwrap().flush(),
         }
     }
 }